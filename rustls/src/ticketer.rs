@@ -1,11 +1,20 @@
 use crate::rand;
 use crate::server::ProducesTickets;
 
-use ring::aead;
+use ring::{aead, digest, hkdf};
+use std::collections::VecDeque;
 use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time;
 
+/// A conservative cap on the number of messages a single random-nonce
+/// AEAD key is used to protect.  This is kept well under the 2^32
+/// birthday bound for 96-bit nonce collisions, so that even a
+/// high-throughput server cannot exhaust the nonce space of a single
+/// ticket key before it is rolled.
+const MAXIMUM_CIPHERTEXTS_PER_KEY: u64 = 1 << 28;
+
 /// The timebase for expiring and rolling tickets and ticketing
 /// keys.  This is UNIX wall time in seconds.
 pub fn timebase() -> u64 {
@@ -23,6 +32,7 @@ pub struct AeadTicketer {
     alg: &'static aead::Algorithm,
     key: aead::LessSafeKey,
     lifetime: u32,
+    remaining_invocations: AtomicU64,
 }
 
 impl AeadTicketer {
@@ -38,10 +48,30 @@ impl AeadTicketer {
             alg,
             key: aead::LessSafeKey::new(key),
             lifetime: 60 * 60 * 12,
+            remaining_invocations: AtomicU64::new(MAXIMUM_CIPHERTEXTS_PER_KEY),
         })
     }
 }
 
+/// A `ProducesTickets` implementation that can report when its own
+/// usage-bounded key lifetime has been spent, so `TicketSwitcher` can roll
+/// it proactively instead of waiting to be refused by `encrypt`.
+///
+/// Implementations without a meaningful usage bound (for example
+/// `HkdfTicketer`, whose keys are not shared across tickets) can rely on
+/// the default, which never asks for an early roll.
+trait ExhaustibleTicketer: ProducesTickets {
+    fn exhausted(&self) -> bool {
+        false
+    }
+}
+
+impl ExhaustibleTicketer for AeadTicketer {
+    fn exhausted(&self) -> bool {
+        self.remaining_invocations.load(Ordering::SeqCst) == 0
+    }
+}
+
 impl ProducesTickets for AeadTicketer {
     fn enabled(&self) -> bool {
         true
@@ -51,7 +81,22 @@ impl ProducesTickets for AeadTicketer {
     }
 
     /// Encrypt `message` and return the ciphertext.
+    ///
+    /// Returns `None` once the usage-bounded key lifetime has been spent,
+    /// in addition to the usual sealing failure cases.
     fn encrypt(&self, message: &[u8]) -> Option<Vec<u8>> {
+        // Atomically claim one use of this key; refuse once they're gone,
+        // so that a single ChaCha20-Poly1305 key is never used for more
+        // seals than our random-nonce birthday bound allows.
+        let claimed = self
+            .remaining_invocations
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                count.checked_sub(1)
+            });
+        if claimed.is_err() {
+            return None;
+        }
+
         // Random nonce, because a counter is a privacy leak.
         let mut nonce_buf = [0u8; 12];
         rand::fill_random(&mut nonce_buf).unwrap();
@@ -102,19 +147,410 @@ impl ProducesTickets for AeadTicketer {
     }
 }
 
+#[test]
+fn aead_ticketer_refuses_to_encrypt_once_budget_is_spent() {
+    let mut t = AeadTicketer::new().unwrap();
+    t.remaining_invocations = AtomicU64::new(1);
+
+    assert!(t.encrypt(b"one").is_some());
+    assert!(t.encrypt(b"two").is_none());
+}
+
+/// Identifies the sealing algorithm in an `HkdfTicketer`'s self-describing
+/// ticket header.  `0` is reserved for "unrecognised".
+fn algorithm_id(alg: &'static aead::Algorithm) -> u8 {
+    if std::ptr::eq(alg, &aead::CHACHA20_POLY1305) {
+        1
+    } else if std::ptr::eq(alg, &aead::AES_256_GCM) {
+        2
+    } else {
+        0
+    }
+}
+
+fn algorithm_from_id(id: u8) -> Option<&'static aead::Algorithm> {
+    match id {
+        1 => Some(&aead::CHACHA20_POLY1305),
+        2 => Some(&aead::AES_256_GCM),
+        _ => None,
+    }
+}
+
+/// A `ring::hkdf::KeyType` of a fixed, runtime-determined length, used to
+/// size the AEAD subkey expanded out of an `HkdfTicketer`'s master secret.
+struct AeadKeyLen(usize);
+
+impl hkdf::KeyType for AeadKeyLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+const HKDF_SALT_LEN: usize = 16;
+const HKDF_SUBKEY_INFO: &[u8] = b"rustls ticketer hkdf subkey";
+
+/// A `ProducesTickets` implementation which holds one long-lived master
+/// secret and derives a fresh, independent AEAD key for every ticket via
+/// HKDF (RFC 5869), rather than sealing every ticket under one static
+/// key.  Because each ticket is effectively protected by its own key,
+/// a single configured master secret safely protects far more tickets
+/// than `AeadTicketer` can, letting `TicketSwitcher` roll it much less
+/// often.
+///
+/// Ticket ciphertexts are self-describing: `alg_id || salt_len || salt
+/// || nonce || ciphertext || tag`, so `decrypt` needs no external state
+/// beyond the master secret.
+pub struct HkdfTicketer {
+    alg: &'static aead::Algorithm,
+    master: Vec<u8>,
+    lifetime: u32,
+}
+
+impl HkdfTicketer {
+    /// Make a ticketer with recommended configuration (ChaCha20-Poly1305
+    /// subkeys) and a random master secret.
+    pub fn new() -> Result<HkdfTicketer, rand::GetRandomFailed> {
+        Self::new_with_algorithm(&aead::CHACHA20_POLY1305)
+    }
+
+    /// Make a ticketer which seals tickets with `alg`, and a random
+    /// master secret.
+    pub fn new_with_algorithm(
+        alg: &'static aead::Algorithm,
+    ) -> Result<HkdfTicketer, rand::GetRandomFailed> {
+        let mut master = vec![0u8; 32];
+        rand::fill_random(&mut master)?;
+
+        Ok(HkdfTicketer {
+            alg,
+            master,
+            lifetime: 60 * 60 * 12,
+        })
+    }
+
+    /// Derive the per-ticket subkey for `salt` from the master secret.
+    fn derive_key(&self, salt: &[u8]) -> aead::LessSafeKey {
+        let mut key_bytes = vec![0u8; self.alg.key_len()];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+            .extract(&self.master)
+            .expand(&[HKDF_SUBKEY_INFO], AeadKeyLen(self.alg.key_len()))
+            .unwrap()
+            .fill(&mut key_bytes)
+            .unwrap();
+
+        aead::LessSafeKey::new(aead::UnboundKey::new(self.alg, &key_bytes).unwrap())
+    }
+}
+
+impl ProducesTickets for HkdfTicketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn lifetime(&self) -> u32 {
+        self.lifetime
+    }
+
+    /// Derive a fresh per-ticket key and encrypt `message` under it.
+    fn encrypt(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let mut salt = [0u8; HKDF_SALT_LEN];
+        rand::fill_random(&mut salt).ok()?;
+
+        let mut nonce_buf = [0u8; 12];
+        rand::fill_random(&mut nonce_buf).ok()?;
+
+        let key = self.derive_key(&salt);
+        let nonce = aead::Nonce::assume_unique_for_key(nonce_buf);
+        let aad = aead::Aad::empty();
+
+        let header_len = 2 + salt.len();
+        let mut ciphertext = Vec::with_capacity(
+            header_len + nonce_buf.len() + message.len() + key.algorithm().tag_len(),
+        );
+        ciphertext.push(algorithm_id(self.alg));
+        ciphertext.push(salt.len() as u8);
+        ciphertext.extend(&salt);
+        ciphertext.extend(&nonce_buf);
+        ciphertext.extend(message);
+
+        let message_start = header_len + nonce_buf.len();
+        key.seal_in_place_separate_tag(nonce, aad, &mut ciphertext[message_start..])
+            .map(|tag| {
+                ciphertext.extend(tag.as_ref());
+                ciphertext
+            })
+            .ok()
+    }
+
+    /// Read the salt from the header, re-derive the matching per-ticket
+    /// key, and open the ciphertext.
+    fn decrypt(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        if ciphertext.len() < 2 {
+            return None;
+        }
+
+        // `Option::is_none_or` postdates this crate's MSRV, so spell this
+        // out with `map_or` instead.
+        #[allow(clippy::unnecessary_map_or)]
+        if algorithm_from_id(ciphertext[0]).map_or(true, |alg| !std::ptr::eq(alg, self.alg)) {
+            return None;
+        }
+
+        let salt_len = ciphertext[1] as usize;
+        let nonce_len = self.alg.nonce_len();
+        let tag_len = self.alg.tag_len();
+        let header_len = 2 + salt_len;
+
+        if ciphertext.len() < header_len + nonce_len + tag_len {
+            return None;
+        }
+
+        let salt = &ciphertext[2..header_len];
+        let nonce_bytes = &ciphertext[header_len..header_len + nonce_len];
+        let key = self.derive_key(salt);
+        let nonce = aead::Nonce::try_assume_unique_for_key(nonce_bytes).ok()?;
+        let aad = aead::Aad::empty();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&ciphertext[header_len + nonce_len..]);
+
+        let plain_len = key.open_in_place(nonce, aad, &mut out).ok()?.len();
+        out.truncate(plain_len);
+        Some(out)
+    }
+}
+
+// Each ticket is sealed under its own HKDF-derived key, so there's no
+// shared key whose usage bound could be spent; the default (never
+// exhausted) is correct here.
+impl ExhaustibleTicketer for HkdfTicketer {}
+
+#[test]
+fn hkdf_ticketer_round_trips() {
+    let t = HkdfTicketer::new().unwrap();
+    let cipher = t.encrypt(b"hello hkdf").unwrap();
+    assert_eq!(t.decrypt(&cipher).unwrap(), b"hello hkdf");
+}
+
+#[test]
+fn hkdf_ticketer_rejects_tampered_ciphertext() {
+    let t = HkdfTicketer::new().unwrap();
+    let mut cipher = t.encrypt(b"hello hkdf").unwrap();
+    let last = cipher.len() - 1;
+    cipher[last] ^= 0xff;
+    assert!(t.decrypt(&cipher).is_none());
+}
+
+/// Detects replayed presentations of an already-accepted, single-use
+/// ticket ciphertext, such as a session ticket redeemed for 0-RTT data.
+///
+/// Implementations need only be probabilistically correct: rejecting a
+/// fresh ticket merely costs a full handshake (a false positive is
+/// tolerable), but accepting a replayed ticket is not (no false
+/// negatives).
+///
+/// When wired into `TicketSwitcher::new_with_generations`, every
+/// successful `decrypt` is checked, not just ones associated with 0-RTT
+/// early data.  This means a legitimate client that deliberately presents
+/// the same ticket more than once -- e.g. several connections reusing one
+/// cached ticket for ordinary (non-0-RTT) resumption -- will have the
+/// second and subsequent presentations rejected as replays, falling back
+/// to a full handshake.  This is a deliberate trade-off for servers that
+/// want single-use tickets; it is not limited to early-data tickets.
+pub trait AntiReplay: Send + Sync {
+    /// Record `ciphertext` as seen and report whether this is the first
+    /// time it has been presented.  Returns `true` if `ciphertext` may be
+    /// accepted, or `false` if it looks like a replay and must be
+    /// rejected.
+    fn check_and_insert(&self, ciphertext: &[u8]) -> bool;
+}
+
+/// A fixed-size Bloom filter over byte strings, using double hashing
+/// (Kirsch/Mitzenmacher) to derive `num_hashes` bit positions from a
+/// single `SHA-256` digest of the input.
+struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: usize,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    fn new(num_bits: usize, num_hashes: u32) -> Self {
+        // `usize::div_ceil` stabilized well after this crate's MSRV, so
+        // spell the rounding-up division out by hand.
+        #[allow(clippy::manual_div_ceil)]
+        let words = (num_bits.max(1) + 63) / 64;
+        BloomFilter {
+            bits: vec![0u64; words],
+            num_bits: num_bits.max(1),
+            num_hashes: num_hashes.max(1),
+        }
+    }
+
+    fn bit_indices(num_bits: usize, num_hashes: u32, data: &[u8]) -> Vec<usize> {
+        let digest = digest::digest(&digest::SHA256, data);
+        let bytes = digest.as_ref();
+        let h1 = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+
+        (0..u64::from(num_hashes))
+            .map(|i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits as u64) as usize)
+            .collect()
+    }
+
+    fn insert(&mut self, data: &[u8]) {
+        for idx in Self::bit_indices(self.num_bits, self.num_hashes, data) {
+            self.bits[idx / 64] |= 1 << (idx % 64);
+        }
+    }
+
+    fn contains(&self, data: &[u8]) -> bool {
+        Self::bit_indices(self.num_bits, self.num_hashes, data)
+            .into_iter()
+            .all(|idx| self.bits[idx / 64] & (1 << (idx % 64)) != 0)
+    }
+}
+
+struct StrikeRegisterState {
+    current: BloomFilter,
+    previous: BloomFilter,
+    next_rotate_time: u64,
+}
+
+/// A practical `AntiReplay` implementation: a pair of rotating Bloom
+/// filters (a "strike register").  Accepted ticket ciphertexts are
+/// recorded in the current filter; periodically -- tied to the same
+/// timebase used by `TicketSwitcher` -- the current filter is rotated
+/// into the previous slot and a fresh one started, so memory use stays
+/// bounded while still covering at least one full ticket lifetime.
+/// Queries check both filters; a hit in either is treated as a replay.
+///
+/// See the trade-off noted on `AntiReplay`: this rejects replays of
+/// *any* ticket, including ones redeemed for ordinary resumption rather
+/// than 0-RTT data.
+pub struct StrikeRegister {
+    num_bits: usize,
+    num_hashes: u32,
+    period: u32,
+    state: Mutex<StrikeRegisterState>,
+}
+
+impl StrikeRegister {
+    /// Construct a strike register sized for roughly `expected_items`
+    /// distinct tickets per rotation `period` (in seconds -- this should
+    /// match the protected ticketer's `lifetime`), at approximately
+    /// `false_positive_rate`.
+    pub fn new(expected_items: usize, false_positive_rate: f64, period: u32) -> Self {
+        let (num_bits, num_hashes) = Self::size_for(expected_items.max(1), false_positive_rate);
+        StrikeRegister {
+            num_bits,
+            num_hashes,
+            period,
+            state: Mutex::new(StrikeRegisterState {
+                current: BloomFilter::new(num_bits, num_hashes),
+                previous: BloomFilter::new(num_bits, num_hashes),
+                next_rotate_time: timebase() + u64::from(period),
+            }),
+        }
+    }
+
+    /// Standard Bloom filter sizing: `m = -(n * ln(p)) / (ln 2)^2` bits
+    /// and `k = (m / n) * ln 2` hash functions.
+    fn size_for(expected_items: usize, false_positive_rate: f64) -> (usize, u32) {
+        let n = expected_items as f64;
+        let p = false_positive_rate.max(f64::MIN_POSITIVE);
+        let ln2 = std::f64::consts::LN_2;
+        let m = -(n * p.ln()) / (ln2 * ln2);
+        let k = (m / n) * ln2;
+        (m.ceil().max(64.0) as usize, (k.round().max(1.0)) as u32)
+    }
+
+    fn maybe_rotate(&self, state: &mut MutexGuard<StrikeRegisterState>) {
+        let now = timebase();
+        if now > state.next_rotate_time {
+            state.previous = mem::replace(
+                &mut state.current,
+                BloomFilter::new(self.num_bits, self.num_hashes),
+            );
+            state.next_rotate_time = now + u64::from(self.period);
+        }
+    }
+}
+
+impl AntiReplay for StrikeRegister {
+    fn check_and_insert(&self, ciphertext: &[u8]) -> bool {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            // Fail closed: if the lock is poisoned we can no longer
+            // guarantee we'd detect a replay.
+            Err(..) => return false,
+        };
+
+        self.maybe_rotate(&mut state);
+
+        let key = digest::digest(&digest::SHA256, ciphertext);
+        let key = key.as_ref();
+        if state.current.contains(key) || state.previous.contains(key) {
+            return false;
+        }
+
+        state.current.insert(key);
+        true
+    }
+}
+
+#[test]
+fn strike_register_detects_replay_and_forgets_after_two_rotations() {
+    let sr = StrikeRegister::new(16, 0.01, 60);
+    let ciphertext = b"ticket-1";
+
+    assert!(sr.check_and_insert(ciphertext));
+    assert!(!sr.check_and_insert(ciphertext));
+
+    // Force a rotation as if `period` had elapsed: the ticket moves into
+    // the `previous` filter and is still caught as a replay.
+    sr.state.lock().unwrap().next_rotate_time = 0;
+    assert!(!sr.check_and_insert(ciphertext));
+
+    // A second rotation pushes it off both filters.
+    sr.state.lock().unwrap().next_rotate_time = 0;
+    assert!(sr.check_and_insert(ciphertext));
+}
+
+/// A previous `current` ticketer, retained only for decryption, together
+/// with the time at which it was demoted.
+struct Generation {
+    ticketer: Box<dyn ExhaustibleTicketer>,
+    created_at: u64,
+}
+
 struct TicketSwitcherState {
-    current: Box<dyn ProducesTickets>,
-    previous: Option<Box<dyn ProducesTickets>>,
+    current: Box<dyn ExhaustibleTicketer>,
+    // Retired generations, newest first.  Bounded in length by
+    // `TicketSwitcher::max_generations` and in age by
+    // `TicketSwitcher::acceptance_window`.
+    retired: VecDeque<Generation>,
     next_switch_time: u64,
 }
 
-/// A ticketer that has a 'current' sub-ticketer and a single
-/// 'previous' ticketer.  It creates a new ticketer every so
-/// often, demoting the current ticketer.
+/// A ticketer that has a 'current' sub-ticketer, plus a bounded ring of
+/// retired generations kept around purely for decryption.  It creates a
+/// new ticketer every so often, demoting the current ticketer into the
+/// ring.
+///
+/// Splitting "how often we roll the encrypting key" (`lifetime`) from
+/// "how long a generation stays decryptable" (`acceptance_window`) lets
+/// operators roll keys aggressively (for usage-bounded safety, see
+/// `AeadTicketer`) without shrinking the window in which already-issued
+/// tickets remain redeemable -- similar to how QUIC stacks retain prior
+/// key phases for in-flight traffic.
 pub struct TicketSwitcher {
-    generator: fn() -> Result<Box<dyn ProducesTickets>, rand::GetRandomFailed>,
+    generator: fn() -> Result<Box<dyn ExhaustibleTicketer>, rand::GetRandomFailed>,
     lifetime: u32,
+    max_generations: usize,
+    acceptance_window: u64,
     state: Mutex<TicketSwitcherState>,
+    anti_replay: Option<Box<dyn AntiReplay>>,
 }
 
 impl TicketSwitcher {
@@ -124,22 +560,78 @@ impl TicketSwitcher {
     /// `ProducesTickets` implementation.
     pub fn new(
         lifetime: u32,
-        generator: fn() -> Result<Box<dyn ProducesTickets>, rand::GetRandomFailed>,
+        generator: fn() -> Result<Box<dyn ExhaustibleTicketer>, rand::GetRandomFailed>,
+    ) -> Result<TicketSwitcher, rand::GetRandomFailed> {
+        Self::new_with_generations(lifetime, generator, 1, u64::from(lifetime) * 2, None)
+    }
+
+    /// As `new()`, but keeps up to `max_generations` retired ticketers
+    /// (instead of just one `previous`), each accepted for decryption
+    /// until `acceptance_window` seconds after it was demoted, and
+    /// additionally rejects any ticket ciphertext that `anti_replay`
+    /// reports as having been seen before.
+    ///
+    /// This decouples the rotation period of the encrypting key from the
+    /// acceptance window of already-issued tickets, and is how a server
+    /// refuses replayed tickets (and so replayed 0-RTT data) without any
+    /// application involvement.
+    pub fn new_with_generations(
+        lifetime: u32,
+        generator: fn() -> Result<Box<dyn ExhaustibleTicketer>, rand::GetRandomFailed>,
+        max_generations: usize,
+        acceptance_window: u64,
+        anti_replay: Option<Box<dyn AntiReplay>>,
     ) -> Result<TicketSwitcher, rand::GetRandomFailed> {
         Ok(TicketSwitcher {
             generator,
             lifetime,
+            max_generations: max_generations.max(1),
+            acceptance_window,
             state: Mutex::new(TicketSwitcherState {
                 current: generator()?,
-                previous: None,
+                retired: VecDeque::new(),
                 next_switch_time: timebase() + u64::from(lifetime),
             }),
+            anti_replay,
         })
     }
 
-    /// If it's time, demote the `current` ticketer to `previous` (so it
-    /// does no new encryptions but can do decryption) and make a fresh
-    /// `current` ticketer.
+    /// Demote the `current` ticketer into the retired ring (so it does no
+    /// new encryptions but can still do decryption), make a fresh
+    /// `current` ticketer, and evict any generation that has fallen
+    /// outside `acceptance_window` or off the end of `max_generations`.
+    fn roll(
+        &self,
+        state: &mut MutexGuard<TicketSwitcherState>,
+    ) -> Result<(), rand::GetRandomFailed> {
+        let now = timebase();
+        let retiring = mem::replace(&mut state.current, (self.generator)()?);
+        state.retired.push_front(Generation {
+            ticketer: retiring,
+            created_at: now,
+        });
+        state.next_switch_time = now + u64::from(self.lifetime);
+
+        while state.retired.len() > self.max_generations {
+            state.retired.pop_back();
+        }
+        // `Option::is_some_and` postdates this crate's MSRV, so spell
+        // this out with `map_or` instead.
+        #[allow(clippy::unnecessary_map_or)]
+        while state.retired.back().map_or(false, |oldest| {
+            now.saturating_sub(oldest.created_at) > self.acceptance_window
+        }) {
+            state.retired.pop_back();
+        }
+        Ok(())
+    }
+
+    /// If it's time, roll the keys.
+    ///
+    /// This rolls when either `next_switch_time` has passed, or the
+    /// `current` ticketer reports (via `ExhaustibleTicketer::exhausted`)
+    /// that its own usage-bounded key lifetime (see `AeadTicketer`) has
+    /// been spent, whichever comes first.
     ///
     /// Calling this regularly will ensure timely key erasure.  Otherwise,
     /// key erasure will be delayed until the next encrypt/decrypt call.
@@ -147,11 +639,8 @@ impl TicketSwitcher {
         &self,
         state: &mut MutexGuard<TicketSwitcherState>,
     ) -> Result<(), rand::GetRandomFailed> {
-        let now = timebase();
-
-        if now > state.next_switch_time {
-            state.previous = Some(mem::replace(&mut state.current, (self.generator)()?));
-            state.next_switch_time = now + u64::from(self.lifetime);
+        if timebase() > state.next_switch_time || state.current.exhausted() {
+            self.roll(state)?;
         }
         Ok(())
     }
@@ -159,7 +648,7 @@ impl TicketSwitcher {
 
 impl ProducesTickets for TicketSwitcher {
     fn lifetime(&self) -> u32 {
-        self.lifetime * 2
+        self.acceptance_window as u32
     }
 
     fn enabled(&self) -> bool {
@@ -179,23 +668,83 @@ impl ProducesTickets for TicketSwitcher {
 
         self.maybe_roll(&mut state).ok()?;
 
-        // Decrypt with the current key; if that fails, try with the previous.
-        state
-            .current
-            .decrypt(ciphertext)
-            .or_else(|| {
-                state
-                    .previous
-                    .as_ref()
-                    .and_then(|previous| previous.decrypt(ciphertext))
-            })
+        // Decrypt with the current key; if that fails, walk the retired
+        // ring newest-to-oldest, skipping any generation already past
+        // `acceptance_window` that no roll has evicted yet.
+        let now = timebase();
+        let plain = state.current.decrypt(ciphertext).or_else(|| {
+            state
+                .retired
+                .iter()
+                .filter(|generation| {
+                    now.saturating_sub(generation.created_at) <= self.acceptance_window
+                })
+                .find_map(|generation| generation.ticketer.decrypt(ciphertext))
+        })?;
+
+        if let Some(anti_replay) = &self.anti_replay {
+            if !anti_replay.check_and_insert(ciphertext) {
+                return None;
+            }
+        }
+
+        Some(plain)
+    }
+}
+
+#[test]
+fn ticket_switcher_evicts_generations_past_the_cap() {
+    let t = TicketSwitcher::new_with_generations(1, generate_inner, 2, 100, None).unwrap();
+    let force_roll = || t.state.lock().unwrap().next_switch_time = 0;
+
+    let cipher_a = t.encrypt(b"a").unwrap();
+    force_roll();
+    let cipher_b = t.encrypt(b"b").unwrap();
+    force_roll();
+    let _cipher_c = t.encrypt(b"c").unwrap();
+
+    // `max_generations == 2`, so `a`'s generation is still within the
+    // cap (retired holds the generations that produced `b` and `a`).
+    assert!(t.decrypt(&cipher_a).is_some());
+
+    force_roll();
+    let _cipher_d = t.encrypt(b"d").unwrap();
+
+    // Rolling again pushes `a`'s generation off the end of the ring.
+    assert!(t.decrypt(&cipher_a).is_none());
+    assert!(t.decrypt(&cipher_b).is_some());
+}
+
+#[test]
+fn ticket_switcher_evicts_generations_past_the_acceptance_window() {
+    // A generous `max_generations` cap, so the count-based eviction
+    // exercised above can't be what evicts anything here.
+    let t = TicketSwitcher::new_with_generations(1, generate_inner, 10, 5, None).unwrap();
+
+    let cipher_a = t.encrypt(b"a").unwrap();
+    t.state.lock().unwrap().next_switch_time = 0;
+    let _cipher_b = t.encrypt(b"b").unwrap();
+
+    // `a`'s generation is retired but still well within the 5-second
+    // acceptance window, so it remains decryptable.
+    assert!(t.decrypt(&cipher_a).is_some());
+
+    // Backdate it past the acceptance window, then trigger another roll
+    // (which is what runs eviction).
+    {
+        let mut state = t.state.lock().unwrap();
+        state.retired.back_mut().unwrap().created_at = 0;
+        state.next_switch_time = 0;
     }
+    let _cipher_c = t.encrypt(b"c").unwrap();
+
+    assert!(t.decrypt(&cipher_a).is_none());
 }
 
 /// A concrete, safe ticket creation mechanism.
 pub struct Ticketer {}
 
-fn generate_inner() -> Result<Box<dyn ProducesTickets>, rand::GetRandomFailed> {
+fn generate_inner() -> Result<Box<dyn ExhaustibleTicketer>, rand::GetRandomFailed> {
     Ok(Box::new(AeadTicketer::new()?))
 }
 